@@ -1,7 +1,9 @@
+mod timer;
 mod utils;
 
 use js_sys::Math;
 use std::fmt;
+use timer::Timer;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
@@ -16,19 +18,56 @@ const GRID_COLOR: &str = "#CCCCCC";
 const DEAD_COLOR: &str = "#FFFFFF";
 const ALIVE_COLOR: &str = "#000000";
 
+// Number of bits packed into each word of the bitset-backed cell storage.
+const BITS_PER_WORD: usize = 32;
+
 #[wasm_bindgen]
 pub struct Universe {
     canvas_id: String,
     context: web_sys::CanvasRenderingContext2d,
     height: u32,
     width: u32,
-    cells: Vec<Cell>,
+    // One bit per cell, packed into words so the whole grid can be handed
+    // to JS as a flat buffer instead of one byte per cell.
+    cells: Vec<u32>,
+    // Reused as the write target for `tick` so each generation doesn't
+    // need to allocate a fresh grid; swapped with `cells` once filled.
+    scratch: Vec<u32>,
+    // Golly-style B/S notation, e.g. "B3/S23". `birth` and `survive` are
+    // the same rule compiled into bitmasks, where bit `n` set means "a
+    // neighbour count of `n` satisfies this half of the rule".
+    rule: String,
+    birth: u16,
+    survive: u16,
+    // Generations since each cell last changed state; reset to 0 on a
+    // flip, saturating otherwise. Drives `draw_cells_heatmap`.
+    age: Vec<u8>,
+    // Source of randomness for `random_mutate`/`random_mutate_seeded`, so a
+    // given seed always reproduces the same starting field.
+    rng: Rng,
+    // Alive probability used when randomizing the grid, in [0.0, 1.0].
+    density: f64,
 }
 
 #[wasm_bindgen]
 impl Universe {
     pub fn new(canvas_id: String, width: u32, height: u32) -> Self {
-        let cells = random_cells(width, height);
+        let seed = random_seed();
+        Self::new_seeded(canvas_id, width, height, seed, 0.5)
+    }
+
+    /// Like `new`, but the starting field is generated from `seed` and
+    /// `density` (the alive probability, in `[0.0, 1.0]`) instead of an
+    /// unreproducible call to `Math.random()`.
+    pub fn new_seeded(canvas_id: String, width: u32, height: u32, seed: u64, density: f64) -> Self {
+        let mut rng = Rng::new(seed);
+        let cells = random_cells(width, height, &mut rng, density);
+        let scratch = vec![0; cells.len()];
+        let rule = "B3/S23".to_string();
+        let (birth, survive) = parse_rule(&rule);
+        // u8::MAX marks "never flipped" so the first heatmap frame renders
+        // untouched cells as fully settled rather than freshly-dead.
+        let age = vec![u8::MAX; (width * height) as usize];
 
         let document = web_sys::window().unwrap().document().unwrap();
         let canvas = document.get_element_by_id(&canvas_id).unwrap();
@@ -54,14 +93,52 @@ impl Universe {
             height,
             width,
             cells,
+            scratch,
+            rule,
+            birth,
+            survive,
+            age,
+            rng,
+            density,
         }
     }
 
+    /// Set the birth/survival rule using Golly-style B/S notation, e.g.
+    /// `"B3/S23"` (Conway's Life) or `"B36/S23"` (HighLife).
+    pub fn set_rule(&mut self, rule: &str) {
+        let (birth, survive) = parse_rule(rule);
+        self.rule = rule.to_string();
+        self.birth = birth;
+        self.survive = survive;
+    }
+
+    pub fn rule(&self) -> String {
+        self.rule.clone()
+    }
+
     pub fn random_mutate(&mut self) {
-        self.cells = random_cells(self.width, self.height)
+        self.random_mutate_seeded(random_seed())
+    }
+
+    /// Like `random_mutate`, but reproducibly: the same seed always yields
+    /// the same field for a given width/height/density.
+    pub fn random_mutate_seeded(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.cells = random_cells(self.width, self.height, &mut self.rng, self.density);
+        self.age = vec![u8::MAX; (self.width * self.height) as usize];
+    }
+
+    pub fn set_density(&mut self, density: f64) {
+        self.density = density;
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
     }
 
     pub fn draw_grid(&self) {
+        let _timer = Timer::new("Universe::draw_grid");
+
         let ctx = &self.context;
         ctx.begin_path();
 
@@ -90,6 +167,8 @@ impl Universe {
     }
 
     pub fn draw_cells(&self) {
+        let _timer = Timer::new("Universe::draw_cells");
+
         let ctx = &self.context;
 
         ctx.begin_path();
@@ -101,7 +180,7 @@ impl Universe {
         for row in 0..self.height {
             for col in 0..self.width {
                 let idx = self.get_index(row, col);
-                if self.cells[idx] != Cell::Alive {
+                if !self.is_alive(idx) {
                     continue;
                 }
 
@@ -120,7 +199,7 @@ impl Universe {
         for row in 0..self.height {
             for col in 0..self.width {
                 let idx = self.get_index(row, col);
-                if self.cells[idx] != Cell::Dead {
+                if self.is_alive(idx) {
                     continue;
                 }
 
@@ -134,6 +213,44 @@ impl Universe {
         }
     }
 
+    /// Like `draw_cells`, but colors each cell by how long it's been in
+    /// its current state instead of plain black/white: freshly-born cells
+    /// are bright, long-lived cells fade toward a cool color, and
+    /// recently-dead cells leave a decaying trail back to the background.
+    pub fn draw_cells_heatmap(&self) {
+        let ctx = &self.context;
+
+        ctx.begin_path();
+
+        // Quantize ages into a handful of buckets and batch fill_rect calls
+        // per bucket, the same way draw_cells batches into an alive pass
+        // and a dead pass, so a large grid doesn't pay per-cell fill_style
+        // cost.
+        let mut buckets: Vec<(String, Vec<(u32, u32)>)> = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let color = heatmap_color(self.is_alive(idx), quantize_age(self.age[idx]));
+                match buckets.iter_mut().find(|(c, _)| *c == color) {
+                    Some((_, cells)) => cells.push((row, col)),
+                    None => buckets.push((color, vec![(row, col)])),
+                }
+            }
+        }
+
+        for (color, cells) in &buckets {
+            ctx.set_fill_style(&JsValue::from_str(color));
+            for (row, col) in cells {
+                ctx.fill_rect(
+                    (col * (CELL_SIZE + 1) + 1).into(),
+                    (row * (CELL_SIZE + 1) + 1).into(),
+                    CELL_SIZE.into(),
+                    CELL_SIZE.into(),
+                );
+            }
+        }
+    }
+
     pub fn render(&self) -> String {
         self.to_string()
     }
@@ -143,35 +260,31 @@ impl Universe {
     }
 
     pub fn tick(&mut self) {
-        let mut next = self.cells.clone();
+        let _timer = Timer::new("Universe::tick");
 
         for row in 0..self.height {
             for col in 0..self.width {
                 let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
+                let alive = self.is_alive(idx);
                 let live_neighbors = self.live_neighbor_count(row, col);
+                let mask = 1u16 << live_neighbors;
+
+                let next_alive = if alive {
+                    self.survive & mask != 0
+                } else {
+                    self.birth & mask != 0
+                };
 
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live neighbours
-                    // dies, as if caused by underpopulation.
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    // Rule 2: Any live cell with two or three live neighbours
-                    // lives on to the next generation.
-                    (Cell::Alive, 2 | 3) => Cell::Alive,
-                    // Rule 3: Any live cell with more than three live
-                    // neighbours dies, as if by overpopulation.
-                    (Cell::Alive, x) if x > 3 => Cell::Dead,
-                    // Rule 4: Any dead cell with exactly three live neighbours
-                    // becomes a live cell, as if by reproduction.
-                    (Cell::Dead, 3) => Cell::Alive,
-                    // All other cells remain in the same state.
-                    (otherwise, _) => otherwise,
+                self.age[idx] = if next_alive == alive {
+                    self.age[idx].saturating_add(1)
+                } else {
+                    0
                 };
 
-                next[idx] = next_cell;
+                set_bit(&mut self.scratch, idx, next_alive);
             }
         }
-        self.cells = next
+        std::mem::swap(&mut self.cells, &mut self.scratch);
     }
 
     pub fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
@@ -186,7 +299,7 @@ impl Universe {
                 let neighbour_col = (column + delta_col) % self.width;
                 let idx = self.get_index(neighbour_row, neighbour_col);
 
-                if let Cell::Alive = self.cells[idx] {
+                if self.is_alive(idx) {
                     count += 1;
                 }
             }
@@ -211,7 +324,9 @@ impl Universe {
         let canvas_width = (CELL_SIZE + 1) * width + 1;
         canvas.set_width(canvas_width);
         self.width = width;
-        self.cells = (0..width * self.height).map(|_| Cell::Dead).collect();
+        self.cells = vec![0; word_count((width * self.height) as usize)];
+        self.scratch = vec![0; self.cells.len()];
+        self.age = vec![u8::MAX; (width * self.height) as usize];
     }
 
     pub fn height(&self) -> u32 {
@@ -231,28 +346,67 @@ impl Universe {
         let canvas_height = (CELL_SIZE + 1) * height + 1;
         canvas.set_height(canvas_height);
         self.height = height;
-        self.cells = (0..height * self.width).map(|_| Cell::Dead).collect();
+        self.cells = vec![0; word_count((height * self.width) as usize)];
+        self.scratch = vec![0; self.cells.len()];
+        self.age = vec![u8::MAX; (height * self.width) as usize];
     }
 
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
         let idx = self.get_index(row, column);
-        self.cells[idx].toggle();
+        let alive = self.is_alive(idx);
+        set_bit(&mut self.cells, idx, !alive);
+        self.age[idx] = 0;
     }
 
     pub fn set_alive_cell(&mut self, row: u32, column: u32) {
         let idx = self.get_index(row, column);
-        self.cells[idx].set_alive();
+        if !self.is_alive(idx) {
+            self.age[idx] = 0;
+        }
+        set_bit(&mut self.cells, idx, true);
     }
 
-    pub fn cells(&self) -> *const Cell {
+    /// Pointer to the packed bitset words. Cell `idx` is alive iff
+    /// `words[idx / 32] & (1 << (idx % 32)) != 0`.
+    pub fn cells(&self) -> *const u32 {
         self.cells.as_ptr()
     }
+
+    /// Number of `u32` words backing the bitset returned by `cells()`.
+    pub fn cells_len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Stamp a built-in pattern (`"glider"`, `"lwss"`, `"gosper-glider-gun"`,
+    /// or `"pulsar"`) so its top-left corner lands at `(row, col)`.
+    pub fn insert_pattern(&mut self, name: &str, row: u32, col: u32) {
+        self.insert_rle(pattern_rle(name), row, col);
+    }
+
+    /// Stamp a Life pattern encoded in RLE format so its top-left corner
+    /// lands at `(row, col)`, wrapping around the grid as needed.
+    pub fn insert_rle(&mut self, rle: &str, row: u32, col: u32) {
+        let cells = decode_rle(rle, row, col, self.height, self.width);
+        self.set_cells(&cells);
+    }
 }
 
 impl Universe {
+    fn is_alive(&self, idx: usize) -> bool {
+        get_bit(&self.cells, idx)
+    }
+
     // Get the dead and alive values of the entire universe.
-    pub fn get_cells(&self) -> &[Cell] {
-        &self.cells
+    pub fn get_cells(&self) -> Vec<Cell> {
+        (0..(self.width * self.height) as usize)
+            .map(|idx| {
+                if self.is_alive(idx) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect()
     }
 
     /// Set cells to be alive in a universe by passing the row and column
@@ -260,16 +414,20 @@ impl Universe {
     pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
         for (row, col) in cells.iter().cloned() {
             let idx = self.get_index(row, col);
-            self.cells[idx] = Cell::Alive;
+            if !self.is_alive(idx) {
+                self.age[idx] = 0;
+            }
+            set_bit(&mut self.cells, idx, true);
         }
     }
 }
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.cells.as_slice().chunks(self.width as usize) {
-            for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let symbol = if self.is_alive(idx) { '◼' } else { '◻' };
                 write!(f, "{}", symbol)?;
             }
             writeln!(f)?;
@@ -287,28 +445,277 @@ pub enum Cell {
     Alive = 1,
 }
 
-impl Cell {
-    fn toggle(&mut self) {
-        *self = match *self {
-            Cell::Alive => Cell::Dead,
-            Cell::Dead => Cell::Alive,
+// Generations over which a cell's heatmap color fades to its resting state.
+const HEATMAP_FADE_GENERATIONS: f64 = 24.0;
+// Distinct age groups `draw_cells_heatmap` batches fill_rect calls into, the
+// same way `draw_cells` batches into an alive pass and a dead pass.
+const HEATMAP_COLOR_BUCKETS: u8 = 8;
+
+// Color for `draw_cells_heatmap`: alive cells start bright and cool
+// toward blue as they age, dead cells fade from a dark trail back to the
+// background color.
+fn heatmap_color(alive: bool, age: u8) -> String {
+    let t = (age as f64 / HEATMAP_FADE_GENERATIONS).min(1.0);
+
+    if alive {
+        let r = lerp(255.0, 40.0, t) as u8;
+        let g = lerp(255.0, 90.0, t) as u8;
+        let b = lerp(120.0, 220.0, t) as u8;
+        format!("rgb({}, {}, {})", r, g, b)
+    } else if age as f64 >= HEATMAP_FADE_GENERATIONS {
+        DEAD_COLOR.to_string()
+    } else {
+        let v = lerp(40.0, 255.0, t) as u8;
+        format!("rgb({0}, {0}, {0})", v)
+    }
+}
+
+// Map `age` to one of `HEATMAP_COLOR_BUCKETS` representative values so
+// cells with visually-identical colors share a single fill_style call.
+fn quantize_age(age: u8) -> u8 {
+    let bucket_width = ((HEATMAP_FADE_GENERATIONS as u8) / HEATMAP_COLOR_BUCKETS).max(1);
+    // The last bucket maps to the fade ceiling itself, so settled cells get
+    // their resting color; the buckets below it round down, which yields
+    // exactly `HEATMAP_COLOR_BUCKETS` distinct values.
+    let max_bucket_start = (HEATMAP_FADE_GENERATIONS as u8).saturating_sub(bucket_width);
+    if age >= max_bucket_start {
+        return HEATMAP_FADE_GENERATIONS as u8;
+    }
+    (age / bucket_width) * bucket_width
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+// Parse Golly-style B/S notation (e.g. "B3/S23") into `(birth, survive)`
+// bitmasks, where bit `n` means "a neighbour count of `n` satisfies this
+// half of the rule".
+fn parse_rule(rule: &str) -> (u16, u16) {
+    let mut birth = 0u16;
+    let mut survive = 0u16;
+
+    for part in rule.split('/') {
+        if let Some(digits) = part.strip_prefix('B').or_else(|| part.strip_prefix('b')) {
+            for digit in digits.chars() {
+                let n = digit.to_digit(10).expect("rule digit must be 0-9");
+                birth |= 1 << n;
+            }
+        } else if let Some(digits) = part.strip_prefix('S').or_else(|| part.strip_prefix('s')) {
+            for digit in digits.chars() {
+                let n = digit.to_digit(10).expect("rule digit must be 0-9");
+                survive |= 1 << n;
+            }
+        } else {
+            panic!("invalid rule, expected \"B.../S...\": {}", rule);
         }
     }
 
-    fn set_alive(&mut self) {
-        *self = Cell::Alive
+    (birth, survive)
+}
+
+// Built-in pattern catalog for `insert_pattern`, in standard Life RLE format.
+const GLIDER_RLE: &str = "bob$2bo$3o!";
+const LWSS_RLE: &str = "bo2bo$o4b$o3bo$4o!";
+const GOSPER_GLIDER_GUN_RLE: &str = "24bo11b$22bobo11b$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o14b$2o8bo3bob2o4bobo11b$10bo5bo7bo11b$11bo3bo20b$12b2o!";
+const PULSAR_RLE: &str = "2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$2b3o3b3o2b$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o2b!";
+
+fn pattern_rle(name: &str) -> &'static str {
+    match name {
+        "glider" => GLIDER_RLE,
+        "lwss" => LWSS_RLE,
+        "gosper-glider-gun" => GOSPER_GLIDER_GUN_RLE,
+        "pulsar" => PULSAR_RLE,
+        _ => panic!("unknown pattern: {}", name),
     }
 }
 
-fn random_cells(width: u32, height: u32) -> Vec<Cell> {
-    (0..width * height)
-        .map(|_| {
-            // random bool
-            if Math::random() < 0.5 {
-                Cell::Alive
-            } else {
-                Cell::Dead
+// Decode a Life pattern in RLE format into the set of alive `(row, col)`
+// cells it stamps, with its top-left corner at `(row, col)` and wrapping
+// around a `height x width` grid.
+fn decode_rle(rle: &str, row: u32, col: u32, height: u32, width: u32) -> Vec<(u32, u32)> {
+    let mut dx: u32 = 0;
+    let mut dy: u32 = 0;
+    let mut run: u32 = 0;
+    let mut alive_cells = Vec::new();
+
+    'decode: for line in rle.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('x') || line.starts_with('X') {
+            continue;
+        }
+
+        for token in line.chars() {
+            match token {
+                '0'..='9' => run = run * 10 + token.to_digit(10).unwrap(),
+                'b' | 'B' => {
+                    dx += run.max(1);
+                    run = 0;
+                }
+                'o' | 'O' => {
+                    for _ in 0..run.max(1) {
+                        alive_cells.push((row + dy, col + dx));
+                        dx += 1;
+                    }
+                    run = 0;
+                }
+                '$' => {
+                    dy += run.max(1);
+                    dx = 0;
+                    run = 0;
+                }
+                '!' => break 'decode,
+                _ => {}
             }
-        })
-        .collect::<Vec<_>>()
+        }
+    }
+
+    alive_cells
+        .into_iter()
+        .map(|(r, c)| (r % height, c % width))
+        .collect()
+}
+
+// Number of `u32` words needed to hold `bits` individual bits.
+fn word_count(bits: usize) -> usize {
+    (bits + BITS_PER_WORD - 1) / BITS_PER_WORD
+}
+
+fn get_bit(words: &[u32], idx: usize) -> bool {
+    let word = idx / BITS_PER_WORD;
+    let bit = idx % BITS_PER_WORD;
+    words[word] & (1 << bit) != 0
+}
+
+fn set_bit(words: &mut [u32], idx: usize, alive: bool) {
+    let word = idx / BITS_PER_WORD;
+    let bit = idx % BITS_PER_WORD;
+    if alive {
+        words[word] |= 1 << bit;
+    } else {
+        words[word] &= !(1 << bit);
+    }
+}
+
+fn random_cells(width: u32, height: u32, rng: &mut Rng, density: f64) -> Vec<u32> {
+    let total = (width * height) as usize;
+    let mut words = vec![0; word_count(total)];
+    for idx in 0..total {
+        if rng.next_f64() < density {
+            set_bit(&mut words, idx, true);
+        }
+    }
+    words
+}
+
+// Draws an entropy-seeded value for unseeded construction/randomization,
+// so results still vary run to run without going through `Rng` itself.
+fn random_seed() -> u64 {
+    (Math::random() * u64::MAX as f64) as u64
+}
+
+// A small xorshift64 PRNG: reproducible and allocation-free, unlike
+// `js_sys::Math::random()`, so the same seed always replays the same run.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift requires a non-zero state.
+        Self {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    // Uniform float in `[0.0, 1.0)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_count_rounds_up_to_whole_words() {
+        assert_eq!(word_count(0), 0);
+        assert_eq!(word_count(1), 1);
+        assert_eq!(word_count(32), 1);
+        assert_eq!(word_count(33), 2);
+        assert_eq!(word_count(64), 2);
+    }
+
+    #[test]
+    fn get_set_bit_round_trip() {
+        let mut words = vec![0u32; 2];
+        assert!(!get_bit(&words, 40));
+
+        set_bit(&mut words, 40, true);
+        assert!(get_bit(&words, 40));
+        // Only the targeted bit should flip.
+        assert!(!get_bit(&words, 39));
+        assert!(!get_bit(&words, 41));
+
+        set_bit(&mut words, 40, false);
+        assert!(!get_bit(&words, 40));
+    }
+
+    #[test]
+    fn parse_rule_conway() {
+        let (birth, survive) = parse_rule("B3/S23");
+        assert_eq!(birth, 1 << 3);
+        assert_eq!(survive, (1 << 2) | (1 << 3));
+    }
+
+    #[test]
+    fn parse_rule_highlife() {
+        let (birth, survive) = parse_rule("B36/S23");
+        assert_eq!(birth, (1 << 3) | (1 << 6));
+        assert_eq!(survive, (1 << 2) | (1 << 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_rule_rejects_unknown_segment() {
+        parse_rule("X3/S23");
+    }
+
+    #[test]
+    fn decode_rle_places_glider_at_origin() {
+        let mut cells = decode_rle(GLIDER_RLE, 0, 0, 10, 10);
+        cells.sort();
+        assert_eq!(cells, vec![(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn decode_rle_offsets_and_wraps() {
+        let mut cells = decode_rle(GLIDER_RLE, 8, 9, 10, 10);
+        cells.sort();
+        assert_eq!(cells, vec![(0, 0), (0, 1), (0, 9), (8, 0), (9, 1)]);
+    }
+
+    #[test]
+    fn pattern_rle_resolves_catalog_names() {
+        assert_eq!(pattern_rle("glider"), GLIDER_RLE);
+        assert_eq!(pattern_rle("lwss"), LWSS_RLE);
+        assert_eq!(pattern_rle("gosper-glider-gun"), GOSPER_GLIDER_GUN_RLE);
+        assert_eq!(pattern_rle("pulsar"), PULSAR_RLE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pattern_rle_rejects_unknown_name() {
+        pattern_rle("not-a-real-pattern");
+    }
 }
@@ -0,0 +1,50 @@
+//! RAII `console.time`/`console.timeEnd` scopes, gated behind the
+//! `profiling` feature so call sites don't need to be edited to see
+//! per-phase timings in the browser devtools.
+
+#[cfg(feature = "profiling")]
+mod imp {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console, js_name = time)]
+        fn console_time(name: &str);
+
+        #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
+        fn console_time_end(name: &str);
+    }
+
+    pub struct Timer<'a> {
+        name: &'a str,
+    }
+
+    impl<'a> Timer<'a> {
+        pub fn new(name: &'a str) -> Timer<'a> {
+            console_time(name);
+            Timer { name }
+        }
+    }
+
+    impl<'a> Drop for Timer<'a> {
+        fn drop(&mut self) {
+            console_time_end(self.name);
+        }
+    }
+}
+
+#[cfg(not(feature = "profiling"))]
+mod imp {
+    use std::marker::PhantomData;
+
+    pub struct Timer<'a>(PhantomData<&'a str>);
+
+    impl<'a> Timer<'a> {
+        #[inline]
+        pub fn new(_name: &'a str) -> Timer<'a> {
+            Timer(PhantomData)
+        }
+    }
+}
+
+pub use imp::Timer;